@@ -1,18 +1,32 @@
-use solana_client::rpc_client::RpcClient;
+use solana_client::{
+    rpc_client::RpcClient,
+    rpc_config::{RpcSendTransactionConfig, RpcSignatureStatusConfig},
+    rpc_request::{MAX_GET_SIGNATURE_STATUSES_QUERY_ITEMS, MAX_MULTIPLE_ACCOUNTS},
+};
 use solana_runtime::bank_client::BankClient;
 use solana_sdk::{
+    account::Account,
     client::{AsyncClient, SyncClient},
+    clock::Slot,
+    commitment_config::CommitmentConfig,
     fee_calculator::FeeCalculator,
     hash::Hash,
     message::Message,
     pubkey::Pubkey,
     signature::{Signature, Signer},
-    signers::Signers,
     system_instruction,
     transaction::Transaction,
     transport::{Result, TransportError},
 };
-use solana_transaction_status::TransactionStatus;
+use solana_transaction_status::{
+    EncodedConfirmedTransaction, EncodedTransaction, EncodedTransactionWithStatusMeta,
+    TransactionStatus, UiMessage, UiRawMessage, UiTransaction, UiTransactionEncoding,
+    UiTransactionStatusMeta,
+};
+use std::{
+    thread::sleep,
+    time::{Duration, Instant},
+};
 
 pub trait Client {
     fn async_send_transaction1(&self, transaction: Transaction) -> Result<Signature>;
@@ -27,6 +41,42 @@ pub trait Client {
     fn poll_for_signature1(&self, signature: &Signature) -> Result<()>;
     fn get_balance1(&self, pubkey: &Pubkey) -> Result<u64>;
     fn get_recent_blockhash1(&self) -> Result<(Hash, FeeCalculator)>;
+
+    fn send_transaction_with_config1(
+        &self,
+        transaction: Transaction,
+        config: RpcSendTransactionConfig,
+    ) -> Result<Signature>;
+    fn get_balance_with_commitment1(
+        &self,
+        pubkey: &Pubkey,
+        commitment_config: CommitmentConfig,
+    ) -> Result<u64>;
+    fn get_recent_blockhash_with_commitment1(
+        &self,
+        commitment_config: CommitmentConfig,
+    ) -> Result<(Hash, FeeCalculator)>;
+
+    // Like `get_recent_blockhash1`, but also returns the slot after which the blockhash is
+    // no longer valid for fee calculation, so a caller can tell when a transaction built
+    // against it can no longer land and is safe to rebroadcast.
+    fn get_recent_blockhash_with_last_valid_slot1(&self) -> Result<(Hash, FeeCalculator, Slot)>;
+    fn get_slot1(&self) -> Result<Slot>;
+
+    fn get_confirmed_transaction1(
+        &self,
+        signature: &Signature,
+    ) -> Result<EncodedConfirmedTransaction>;
+
+    fn get_account_data1(&self, pubkey: &Pubkey) -> Result<Vec<u8>>;
+
+    fn get_multiple_accounts1(&self, pubkeys: &[Pubkey]) -> Result<Vec<Option<Account>>>;
+
+    fn get_multiple_accounts_with_commitment1(
+        &self,
+        pubkeys: &[Pubkey],
+        commitment_config: CommitmentConfig,
+    ) -> Result<Vec<Option<Account>>>;
 }
 
 impl Client for RpcClient {
@@ -41,13 +91,26 @@ impl Client for RpcClient {
             .map_err(|e| TransportError::Custom(e.to_string()))
     }
 
+    // The JSON-RPC endpoint rejects `getSignatureStatuses` requests above
+    // `MAX_GET_SIGNATURE_STATUSES_QUERY_ITEMS` signatures, so split a large batch into
+    // chunks and concatenate the results back in input order. Also ask the node to search
+    // its transaction history, so statuses of signatures already evicted from the recent
+    // status cache can still be recovered when a batch tool resumes.
     fn get_signature_statuses1(
         &self,
         signatures: &[Signature],
     ) -> Result<Vec<Option<TransactionStatus>>> {
-        self.get_signature_statuses(signatures)
-            .map(|response| response.value)
-            .map_err(|e| TransportError::Custom(e.to_string()))
+        let config = RpcSignatureStatusConfig {
+            search_transaction_history: true,
+        };
+        let mut statuses = Vec::with_capacity(signatures.len());
+        for chunk in signatures.chunks(MAX_GET_SIGNATURE_STATUSES_QUERY_ITEMS) {
+            let response = self
+                .get_signature_statuses_with_config(chunk, config)
+                .map_err(|e| TransportError::Custom(e.to_string()))?;
+            statuses.extend(response.value);
+        }
+        Ok(statuses)
     }
 
     fn poll_for_signature1(&self, signature: &Signature) -> Result<()> {
@@ -64,6 +127,87 @@ impl Client for RpcClient {
         self.get_recent_blockhash()
             .map_err(|e| TransportError::Custom(e.to_string()))
     }
+
+    fn send_transaction_with_config1(
+        &self,
+        transaction: Transaction,
+        config: RpcSendTransactionConfig,
+    ) -> Result<Signature> {
+        self.send_transaction_with_config(&transaction, config)
+            .map_err(|e| TransportError::Custom(e.to_string()))
+    }
+
+    fn get_balance_with_commitment1(
+        &self,
+        pubkey: &Pubkey,
+        commitment_config: CommitmentConfig,
+    ) -> Result<u64> {
+        self.get_balance_with_commitment(pubkey, commitment_config)
+            .map(|response| response.value)
+            .map_err(|e| TransportError::Custom(e.to_string()))
+    }
+
+    fn get_recent_blockhash_with_commitment1(
+        &self,
+        commitment_config: CommitmentConfig,
+    ) -> Result<(Hash, FeeCalculator)> {
+        self.get_recent_blockhash_with_commitment(commitment_config)
+            .map(|response| {
+                let (blockhash, fee_calculator, _last_valid_slot) = response.value;
+                (blockhash, fee_calculator)
+            })
+            .map_err(|e| TransportError::Custom(e.to_string()))
+    }
+
+    fn get_recent_blockhash_with_last_valid_slot1(&self) -> Result<(Hash, FeeCalculator, Slot)> {
+        self.get_recent_blockhash_with_commitment(CommitmentConfig::default())
+            .map(|response| response.value)
+            .map_err(|e| TransportError::Custom(e.to_string()))
+    }
+
+    fn get_slot1(&self) -> Result<Slot> {
+        self.get_slot().map_err(|e| TransportError::Custom(e.to_string()))
+    }
+
+    fn get_confirmed_transaction1(
+        &self,
+        signature: &Signature,
+    ) -> Result<EncodedConfirmedTransaction> {
+        self.get_confirmed_transaction(signature, UiTransactionEncoding::JsonParsed)
+            .map_err(|e| TransportError::Custom(e.to_string()))
+    }
+
+    // `RpcClient::get_account_data` errors with `AccountNotFound` when the account doesn't
+    // exist, rather than returning empty data -- but callers use an empty result to mean
+    // "not created yet" (see `BankClient`'s impl below), so go through
+    // `get_account_with_commitment` instead and treat a missing account the same way.
+    fn get_account_data1(&self, pubkey: &Pubkey) -> Result<Vec<u8>> {
+        self.get_account_with_commitment(pubkey, CommitmentConfig::default())
+            .map(|response| response.value.map(|account| account.data).unwrap_or_default())
+            .map_err(|e| TransportError::Custom(e.to_string()))
+    }
+
+    fn get_multiple_accounts1(&self, pubkeys: &[Pubkey]) -> Result<Vec<Option<Account>>> {
+        self.get_multiple_accounts_with_commitment1(pubkeys, CommitmentConfig::default())
+    }
+
+    // The JSON-RPC endpoint rejects `getMultipleAccounts` requests above
+    // `MAX_MULTIPLE_ACCOUNTS` pubkeys, so split a large batch into chunks and concatenate
+    // the results back in input order, same as `get_signature_statuses1` above.
+    fn get_multiple_accounts_with_commitment1(
+        &self,
+        pubkeys: &[Pubkey],
+        commitment_config: CommitmentConfig,
+    ) -> Result<Vec<Option<Account>>> {
+        let mut accounts = Vec::with_capacity(pubkeys.len());
+        for chunk in pubkeys.chunks(MAX_MULTIPLE_ACCOUNTS) {
+            let response = self
+                .get_multiple_accounts_with_commitment(chunk, commitment_config)
+                .map_err(|e| TransportError::Custom(e.to_string()))?;
+            accounts.extend(response.value);
+        }
+        Ok(accounts)
+    }
 }
 
 impl Client for BankClient {
@@ -107,6 +251,109 @@ impl Client for BankClient {
     fn get_recent_blockhash1(&self) -> Result<(Hash, FeeCalculator)> {
         self.get_recent_blockhash()
     }
+
+    // A `BankClient` has no preflight simulation or commitment levels of its own; every
+    // transaction lands immediately, so just ignore the config and fall back to the
+    // unconfigured paths.
+    fn send_transaction_with_config1(
+        &self,
+        transaction: Transaction,
+        _config: RpcSendTransactionConfig,
+    ) -> Result<Signature> {
+        self.async_send_transaction1(transaction)
+    }
+
+    fn get_balance_with_commitment1(
+        &self,
+        pubkey: &Pubkey,
+        _commitment_config: CommitmentConfig,
+    ) -> Result<u64> {
+        self.get_balance1(pubkey)
+    }
+
+    fn get_recent_blockhash_with_commitment1(
+        &self,
+        _commitment_config: CommitmentConfig,
+    ) -> Result<(Hash, FeeCalculator)> {
+        self.get_recent_blockhash1()
+    }
+
+    // A `BankClient`'s blockhash never expires out from under an in-process test, so report
+    // the maximum possible slot as the last valid one.
+    fn get_recent_blockhash_with_last_valid_slot1(&self) -> Result<(Hash, FeeCalculator, Slot)> {
+        let (blockhash, fee_calculator) = self.get_recent_blockhash1()?;
+        Ok((blockhash, fee_calculator, Slot::MAX))
+    }
+
+    fn get_slot1(&self) -> Result<Slot> {
+        Ok(0)
+    }
+
+    // A `BankClient` doesn't retain encoded transaction history, so synthesize a minimal
+    // record from the signature status alone. Good enough to drive tests of the
+    // `--verbose` decoding path without a real validator.
+    fn get_confirmed_transaction1(
+        &self,
+        signature: &Signature,
+    ) -> Result<EncodedConfirmedTransaction> {
+        let status = self
+            .get_signature_status(signature)?
+            .ok_or_else(|| TransportError::Custom(format!("unknown signature {}", signature)))?;
+        Ok(EncodedConfirmedTransaction {
+            slot: 0,
+            transaction: EncodedTransactionWithStatusMeta {
+                transaction: EncodedTransaction::Json(UiTransaction {
+                    signatures: vec![signature.to_string()],
+                    message: UiMessage::Raw(UiRawMessage {
+                        header: Default::default(),
+                        account_keys: vec![],
+                        recent_blockhash: Hash::default().to_string(),
+                        instructions: vec![],
+                    }),
+                }),
+                meta: Some(UiTransactionStatusMeta {
+                    err: status.clone().err(),
+                    status,
+                    fee: 0,
+                    pre_balances: vec![],
+                    post_balances: vec![],
+                    inner_instructions: None,
+                    log_messages: None,
+                    pre_token_balances: None,
+                    post_token_balances: None,
+                }),
+            },
+        })
+    }
+
+    fn get_account_data1(&self, pubkey: &Pubkey) -> Result<Vec<u8>> {
+        Ok(self.get_account_data(pubkey)?.unwrap_or_default())
+    }
+
+    // A `BankClient` has no native multiple-accounts RPC, so just look each one up; there's
+    // no query-size limit to respect against an in-process bank.
+    fn get_multiple_accounts1(&self, pubkeys: &[Pubkey]) -> Result<Vec<Option<Account>>> {
+        pubkeys.iter().map(|pubkey| self.get_account(pubkey)).collect()
+    }
+
+    // A `BankClient` has no commitment levels of its own; every transaction lands
+    // immediately, so just ignore the config like `get_balance_with_commitment1` above.
+    fn get_multiple_accounts_with_commitment1(
+        &self,
+        pubkeys: &[Pubkey],
+        _commitment_config: CommitmentConfig,
+    ) -> Result<Vec<Option<Account>>> {
+        self.get_multiple_accounts1(pubkeys)
+    }
+}
+
+// A decoded, human-readable view of a confirmed transaction, used to back a `--verbose`
+// audit of exactly what a distribution transfer did without an external explorer.
+pub struct DecodedTransaction {
+    pub slot: Slot,
+    pub fee: u64,
+    pub log_messages: Vec<String>,
+    pub account_balance_deltas: Vec<(String, i64)>,
 }
 
 pub struct ThinClient<C: Client>(pub C);
@@ -129,16 +376,19 @@ impl<C: Client> ThinClient<C> {
         self.0.send_and_confirm_transaction1(transaction)
     }
 
-    pub fn send_message<S: Signers>(&self, message: Message, signers: &S) -> Result<Signature> {
+    // Accepts a slice of trait-object signers rather than a single concrete `Signers`
+    // type, so a caller can mix signer implementations in one transaction -- e.g. a file
+    // keypair fee payer alongside a `solana-remote-wallet` hardware signer authority.
+    pub fn send_message(&self, message: Message, signers: &[&dyn Signer]) -> Result<Signature> {
         let (blockhash, _fee_caluclator) = self.get_recent_blockhash()?;
         let transaction = Transaction::new(signers, message, blockhash);
         self.send_transaction(transaction)
     }
 
-    pub fn transfer<S: Signer>(
+    pub fn transfer(
         &self,
         lamports: u64,
-        sender_keypair: &S,
+        sender_keypair: &dyn Signer,
         to_pubkey: &Pubkey,
     ) -> Result<Signature> {
         let create_instruction =
@@ -158,4 +408,383 @@ impl<C: Client> ThinClient<C> {
     pub fn get_balance(&self, pubkey: &Pubkey) -> Result<u64> {
         self.0.get_balance1(pubkey)
     }
+
+    // Send a transaction with an explicit `RpcSendTransactionConfig`, letting a caller
+    // skip preflight simulation on many near-identical transactions, or request a
+    // specific preflight commitment level.
+    pub fn send_transaction_with_config(
+        &self,
+        transaction: Transaction,
+        config: RpcSendTransactionConfig,
+    ) -> Result<Signature> {
+        self.0.send_transaction_with_config1(transaction, config)
+    }
+
+    pub fn get_balance_with_commitment(
+        &self,
+        pubkey: &Pubkey,
+        commitment_config: CommitmentConfig,
+    ) -> Result<u64> {
+        self.0.get_balance_with_commitment1(pubkey, commitment_config)
+    }
+
+    pub fn get_recent_blockhash_with_commitment(
+        &self,
+        commitment_config: CommitmentConfig,
+    ) -> Result<(Hash, FeeCalculator)> {
+        self.0
+            .get_recent_blockhash_with_commitment1(commitment_config)
+    }
+
+    pub fn get_recent_blockhash_with_last_valid_slot(&self) -> Result<(Hash, FeeCalculator, Slot)> {
+        self.0.get_recent_blockhash_with_last_valid_slot1()
+    }
+
+    pub fn get_slot(&self) -> Result<Slot> {
+        self.0.get_slot1()
+    }
+
+    pub fn get_account_data(&self, pubkey: &Pubkey) -> Result<Vec<u8>> {
+        self.0.get_account_data1(pubkey)
+    }
+
+    // Fetch many accounts in as few RPC round-trips as the server's batch limit allows,
+    // rather than one request per account -- important when auditing a CSV of thousands of
+    // recipients.
+    pub fn get_multiple_accounts(&self, pubkeys: &[Pubkey]) -> Result<Vec<Option<Account>>> {
+        self.0.get_multiple_accounts1(pubkeys)
+    }
+
+    pub fn get_multiple_accounts_with_commitment(
+        &self,
+        pubkeys: &[Pubkey],
+        commitment_config: CommitmentConfig,
+    ) -> Result<Vec<Option<Account>>> {
+        self.0
+            .get_multiple_accounts_with_commitment1(pubkeys, commitment_config)
+    }
+
+    // Poll `get_signature_statuses` until `signature` is observed at the finalized
+    // commitment level (confirmations == None, i.e. rooted) or `deadline` elapses.
+    //
+    // A transaction that appears failed while still at a merely "confirmed" level may be
+    // on a minority fork, so an `err` is only treated as terminal once the transaction is
+    // rooted. This replaces the naive wait-for-any-status semantics of `poll_for_signature`.
+    pub fn poll_for_finalization(&self, signature: &Signature, deadline: Instant) -> Result<()> {
+        loop {
+            if let Some(status) = self.get_signature_statuses(&[*signature])?.pop().flatten() {
+                if status.confirmations.is_none() {
+                    return status
+                        .status
+                        .map_err(|e| TransportError::Custom(e.to_string()));
+                }
+            }
+            if Instant::now() >= deadline {
+                return Err(TransportError::Custom(format!(
+                    "signature not finalized before deadline: {}",
+                    signature
+                )));
+            }
+            sleep(Duration::from_millis(500));
+        }
+    }
+
+    // Loop until every signature in `signatures` is finalized or `deadline` elapses, so a
+    // batch tool can resume a prior run and reconcile status on startup instead of
+    // re-sending transactions that may already be finalized.
+    pub fn send_and_confirm_many(&self, signatures: &[Signature], deadline: Instant) -> Result<()> {
+        let mut pending = signatures.to_vec();
+        loop {
+            let statuses = self.get_signature_statuses(&pending)?;
+            let mut still_pending = Vec::new();
+            for (signature, opt_status) in pending.iter().zip(statuses.into_iter()) {
+                match opt_status {
+                    Some(status) if status.confirmations.is_none() => {
+                        status
+                            .status
+                            .map_err(|e| TransportError::Custom(e.to_string()))?;
+                    }
+                    _ => still_pending.push(*signature),
+                }
+            }
+            pending = still_pending;
+            if pending.is_empty() {
+                return Ok(());
+            }
+            if Instant::now() >= deadline {
+                return Err(TransportError::Custom(format!(
+                    "{} signature(s) not finalized before deadline",
+                    pending.len()
+                )));
+            }
+            sleep(Duration::from_millis(500));
+        }
+    }
+
+    // Send every `(message, signers)` pair, then poll until each is finalized or its
+    // blockhash expires, rebroadcasting expired transactions against a fresh blockhash
+    // instead of hanging forever on one that was dropped. An expired blockhash guarantees
+    // the prior attempt can never land, so rebroadcasting cannot cause a duplicate
+    // transfer. Gives up on a transaction once it has been rebroadcast `MAX_RETRIES` times
+    // and its blockhash expires once more.
+    //
+    // Returns one `Result` per input transaction, in input order, so a single on-chain
+    // failure or retry exhaustion doesn't stop the caller from learning the fate of the
+    // other transactions in the batch. The outer `Result` only carries errors from the
+    // batch as a whole (an RPC call failing, or the deadline elapsing with transactions
+    // still in flight).
+    pub fn send_and_confirm_with_retry<'a>(
+        &self,
+        transactions: Vec<(Message, Vec<&'a dyn Signer>)>,
+        deadline: Instant,
+    ) -> Result<Vec<Result<Signature>>> {
+        const MAX_RETRIES: usize = 5;
+
+        struct Pending<'a> {
+            index: usize,
+            message: Message,
+            signers: Vec<&'a dyn Signer>,
+            signature: Signature,
+            last_valid_slot: Slot,
+            retries: usize,
+        }
+
+        let mut pending = Vec::with_capacity(transactions.len());
+        for (index, (message, signers)) in transactions.into_iter().enumerate() {
+            let (blockhash, _fee_calculator, last_valid_slot) =
+                self.get_recent_blockhash_with_last_valid_slot()?;
+            let transaction = Transaction::new(&signers, message.clone(), blockhash);
+            let signature = transaction.signatures[0];
+            self.async_send_transaction(transaction)?;
+            pending.push(Pending {
+                index,
+                message,
+                signers,
+                signature,
+                last_valid_slot,
+                retries: 0,
+            });
+        }
+
+        let mut finalized: Vec<Option<Result<Signature>>> =
+            (0..pending.len()).map(|_| None).collect();
+        while !pending.is_empty() {
+            let slot = self.get_slot()?;
+            let signatures: Vec<Signature> = pending.iter().map(|p| p.signature).collect();
+            let statuses = self.get_signature_statuses(&signatures)?;
+
+            let mut still_pending = Vec::new();
+            for (mut pending_tx, opt_status) in pending.into_iter().zip(statuses.into_iter()) {
+                match opt_status {
+                    Some(status) if status.confirmations.is_none() => {
+                        finalized[pending_tx.index] = Some(
+                            status
+                                .status
+                                .map(|()| pending_tx.signature)
+                                .map_err(|e| TransportError::Custom(e.to_string())),
+                        );
+                    }
+                    _ if slot > pending_tx.last_valid_slot => {
+                        pending_tx.retries += 1;
+                        if pending_tx.retries > MAX_RETRIES {
+                            finalized[pending_tx.index] = Some(Err(TransportError::Custom(
+                                format!(
+                                    "transaction {} repeatedly expired after {} retries",
+                                    pending_tx.signature, MAX_RETRIES
+                                ),
+                            )));
+                            continue;
+                        }
+                        let (blockhash, _fee_calculator, last_valid_slot) =
+                            self.get_recent_blockhash_with_last_valid_slot()?;
+                        let transaction = Transaction::new(
+                            &pending_tx.signers,
+                            pending_tx.message.clone(),
+                            blockhash,
+                        );
+                        pending_tx.signature = transaction.signatures[0];
+                        pending_tx.last_valid_slot = last_valid_slot;
+                        self.async_send_transaction(transaction)?;
+                        still_pending.push(pending_tx);
+                    }
+                    _ => still_pending.push(pending_tx),
+                }
+            }
+            pending = still_pending;
+            if pending.is_empty() {
+                break;
+            }
+            if Instant::now() >= deadline {
+                return Err(TransportError::Custom(format!(
+                    "{} transaction(s) not finalized before deadline",
+                    pending.len()
+                )));
+            }
+            sleep(Duration::from_millis(500));
+        }
+        Ok(finalized
+            .into_iter()
+            .map(|result| result.expect("every pending transaction is resolved before the loop exits"))
+            .collect())
+    }
+
+    // Fetch a confirmed transaction and render its fee, log messages, and per-account
+    // balance deltas, so a batch tool user can diagnose a failed or surprising allocation
+    // without reaching for an external explorer.
+    pub fn confirm_transaction_verbose(&self, signature: &Signature) -> Result<DecodedTransaction> {
+        let confirmed = self.0.get_confirmed_transaction1(signature)?;
+        let meta = confirmed.transaction.meta.ok_or_else(|| {
+            TransportError::Custom(format!("no metadata for transaction {}", signature))
+        })?;
+
+        let account_keys: Vec<String> = match &confirmed.transaction.transaction {
+            EncodedTransaction::Json(ui_transaction) => match &ui_transaction.message {
+                UiMessage::Parsed(message) => message
+                    .account_keys
+                    .iter()
+                    .map(|key| key.pubkey.clone())
+                    .collect(),
+                UiMessage::Raw(message) => message.account_keys.clone(),
+            },
+            _ => Vec::new(),
+        };
+
+        let account_balance_deltas = account_keys
+            .into_iter()
+            .zip(meta.pre_balances.iter().zip(meta.post_balances.iter()))
+            .map(|(pubkey, (pre, post))| (pubkey, *post as i64 - *pre as i64))
+            .collect();
+
+        Ok(DecodedTransaction {
+            slot: confirmed.slot,
+            fee: meta.fee,
+            log_messages: meta.log_messages.unwrap_or_default(),
+            account_balance_deltas,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_runtime::bank::Bank;
+    use solana_sdk::{
+        genesis_config::create_genesis_config, native_token::sol_to_lamports, signature::Keypair,
+    };
+
+    #[test]
+    fn test_poll_for_finalization_and_send_and_confirm_many() {
+        let (genesis_config, sender_keypair) = create_genesis_config(sol_to_lamports(1.0));
+        let bank = Bank::new(&genesis_config);
+        let bank_client = BankClient::new(bank);
+        let thin_client = ThinClient(bank_client);
+
+        let recipient = Pubkey::new_unique();
+        let (blockhash, _fee_calculator) = thin_client.get_recent_blockhash().unwrap();
+        let transaction = Transaction::new_signed_instructions(
+            &[&sender_keypair],
+            vec![system_instruction::transfer(
+                &sender_keypair.pubkey(),
+                &recipient,
+                sol_to_lamports(0.1),
+            )],
+            blockhash,
+        );
+        let signature = thin_client
+            .async_send_transaction(transaction)
+            .unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        thin_client
+            .poll_for_finalization(&signature, deadline)
+            .unwrap();
+        assert_eq!(
+            thin_client.get_balance(&recipient).unwrap(),
+            sol_to_lamports(0.1)
+        );
+
+        // `send_and_confirm_many` should treat an already-finalized signature as done on its
+        // first poll, so a batch tool can resume a prior run without re-sending it.
+        thin_client
+            .send_and_confirm_many(&[signature], Instant::now() + Duration::from_secs(5))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_send_and_confirm_with_retry_reports_per_transaction_results() {
+        let (genesis_config, sender_keypair) = create_genesis_config(sol_to_lamports(1.0));
+        let bank = Bank::new(&genesis_config);
+        let bank_client = BankClient::new(bank);
+        let thin_client = ThinClient(bank_client);
+
+        let ok_recipient = Pubkey::new_unique();
+        let ok_message = Message::new(&[system_instruction::transfer(
+            &sender_keypair.pubkey(),
+            &ok_recipient,
+            sol_to_lamports(0.1),
+        )]);
+
+        // A transfer out of an account with no funds fails when it executes. The funded
+        // sender pays the fee, so the transaction lands and resolves to an on-chain error.
+        let failing_sender = Keypair::new();
+        let failing_recipient = Pubkey::new_unique();
+        let failing_message = Message::new_with_payer(
+            &[system_instruction::transfer(
+                &failing_sender.pubkey(),
+                &failing_recipient,
+                sol_to_lamports(0.1),
+            )],
+            Some(&sender_keypair.pubkey()),
+        );
+
+        let results = thin_client
+            .send_and_confirm_with_retry(
+                vec![
+                    (ok_message, vec![&sender_keypair as &dyn Signer]),
+                    (
+                        failing_message,
+                        vec![&sender_keypair as &dyn Signer, &failing_sender as &dyn Signer],
+                    ),
+                ],
+                Instant::now() + Duration::from_secs(5),
+            )
+            .unwrap();
+
+        // The failing transaction's on-chain error must not stop the caller from learning
+        // that the other transaction in the same batch landed.
+        assert!(results[0].is_ok());
+        assert!(results[1].is_err());
+        assert_eq!(
+            thin_client.get_balance(&ok_recipient).unwrap(),
+            sol_to_lamports(0.1)
+        );
+    }
+
+    #[test]
+    fn test_confirm_transaction_verbose_decodes_a_landed_transaction() {
+        let (genesis_config, sender_keypair) = create_genesis_config(sol_to_lamports(1.0));
+        let bank = Bank::new(&genesis_config);
+        let bank_client = BankClient::new(bank);
+        let thin_client = ThinClient(bank_client);
+
+        let recipient = Pubkey::new_unique();
+        let (blockhash, _fee_calculator) = thin_client.get_recent_blockhash().unwrap();
+        let transaction = Transaction::new_signed_instructions(
+            &[&sender_keypair],
+            vec![system_instruction::transfer(
+                &sender_keypair.pubkey(),
+                &recipient,
+                sol_to_lamports(0.1),
+            )],
+            blockhash,
+        );
+        let signature = thin_client.send_transaction(transaction).unwrap();
+
+        // `BankClient` has no transaction history of its own, so `get_confirmed_transaction1`
+        // synthesizes a minimal record from the signature status alone -- this just exercises
+        // that the decode path runs end to end without a real validator.
+        let decoded = thin_client.confirm_transaction_verbose(&signature).unwrap();
+        assert!(decoded.account_balance_deltas.is_empty());
+        assert!(decoded.log_messages.is_empty());
+    }
 }
@@ -1,5 +1,6 @@
-use crate::args::{BalancesArgs, DistributeTokensArgs, StakeArgs, TransactionLogArgs};
+use crate::args::{BalancesArgs, DistributeTokensArgs, SplTokenArgs, StakeArgs, TransactionLogArgs};
 use crate::thin_client::{Client, ThinClient};
+use chrono::{DateTime, Utc};
 use console::style;
 use csv::{ReaderBuilder, Trim};
 use indexmap::IndexMap;
@@ -7,10 +8,15 @@ use indicatif::{ProgressBar, ProgressStyle};
 use itertools::Itertools;
 use pickledb::{PickleDb, PickleDbDumpPolicy};
 use serde::{Deserialize, Serialize};
+use solana_client::rpc_config::RpcSendTransactionConfig;
 use solana_sdk::{
+    clock::Slot,
+    commitment_config::{CommitmentConfig, CommitmentLevel},
     hash::Hash,
     message::Message,
-    native_token::{lamports_to_sol, sol_to_lamports},
+    native_token::{lamports_to_sol, sol_to_lamports, LAMPORTS_PER_SOL},
+    program_pack::Pack,
+    rent::Rent,
     signature::{Signature, Signer},
     system_instruction,
     transaction::Transaction,
@@ -18,40 +24,81 @@ use solana_sdk::{
 };
 use solana_stake_program::{
     stake_instruction,
-    stake_state::{Authorized, Lockup, StakeAuthorize},
+    stake_state::{Authorized, Lockup, LockupArgs, StakeAuthorize, StakeState},
 };
 use solana_transaction_status::TransactionStatus;
+use spl_associated_token_account::{create_associated_token_account, get_associated_token_address};
+use spl_token::state::Mint;
 use std::{cmp, io, path::Path, process, thread::sleep, time::Duration};
 
+// Lamports have 9 decimal places, same as a mint's base units would if it chose to mirror SOL.
+const SOL_DECIMALS: u8 = 9;
+
+// `accepted_amount_dollars` is kept as the raw CSV string so it can be parsed into exact
+// base units, rather than letting serde round it through an `f64` before we ever see it.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 struct Bid {
-    accepted_amount_dollars: f64,
+    accepted_amount_dollars: String,
     primary_address: String,
 }
 
+// Bid CSVs carry dollar amounts with 2 to 4 decimal places.
+const BID_DOLLAR_DECIMALS: u8 = 4;
+
+// The on-disk/CSV representation of an allocation, where `amount` is a decimal string so it
+// can be parsed into exact base units rather than round-tripped through a float. `lockup_date`
+// is an optional RFC 3339 timestamp column, only meaningful for stake distributions.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct CsvAllocation {
+    recipient: String,
+    amount: String,
+    #[serde(default)]
+    lockup_date: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 struct Allocation {
     recipient: String,
-    amount: f64,
+    amount: u64,
+    lockup_date: Option<DateTime<Utc>>,
 }
 
+// One recipient's share of a (possibly batched) transaction.
 #[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
-struct TransactionInfo {
+struct RecipientAllocation {
     recipient: String,
-    amount: f64,
+    amount: u64,
+    lockup_date: Option<DateTime<Utc>>,
+}
+
+// A signature can now fund more than one recipient, since `distribute_tokens` packs several
+// plain transfers into a single transaction. `recipients` holds every allocation that
+// transaction is responsible for, so reconciliation on re-run stays exact per-recipient.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+struct TransactionInfo {
+    recipients: Vec<RecipientAllocation>,
+    decimals: u8,
     new_stake_account_address: String,
-    finalized: bool,
+    // Set to the wall-clock time `update_finalized_transaction` first observed the transaction
+    // reach the run's commitment level: rooted at `finalized`, any observed status at
+    // `confirmed` or weaker. `None` while unconfirmed, still confirming, or purged.
+    finalized_date: Option<DateTime<Utc>>,
     blockhash: String,
+    // The last slot at which `blockhash` is valid for fee calculation. Once the cluster's
+    // root slot passes this without the signature showing up, the transaction can never
+    // land and is safe to treat as expired.
+    last_valid_slot: Slot,
 }
 
 #[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
 struct SignedTransactionInfo {
     recipient: String,
-    amount: f64,
+    amount: String,
     new_stake_account_address: String,
-    finalized: bool,
+    finalized_date: Option<String>,
     blockhash: String,
     signature: String,
+    lockup_date: Option<String>,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -64,6 +111,66 @@ pub enum Error {
     PickleDbError(#[from] pickledb::error::Error),
     #[error("Transport error")]
     TransportError(#[from] TransportError),
+    #[error("Program error")]
+    ProgramError(#[from] solana_sdk::program_error::ProgramError),
+    #[error("{0}")]
+    ParseAmountError(#[from] ParseAmountError),
+    #[error("{0}")]
+    InsufficientBalance(String),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ParseAmountError {
+    #[error("invalid amount `{0}`")]
+    InvalidAmount(String),
+    #[error("`{0}` has more than {1} decimal places")]
+    TooManyDecimals(String, u8),
+}
+
+// Parse a decimal string like "12.345678901" into exact base units, scaling by `decimals`
+// without ever round-tripping through a float. Rejects more fractional digits than
+// `decimals` allows, rather than silently truncating them.
+fn parse_base_units(amount: &str, decimals: u8) -> Result<u64, ParseAmountError> {
+    let mut parts = amount.splitn(2, '.');
+    let whole = parts.next().unwrap();
+    let fraction = parts.next().unwrap_or("");
+    if fraction.len() > decimals as usize {
+        return Err(ParseAmountError::TooManyDecimals(
+            amount.to_string(),
+            decimals,
+        ));
+    }
+    let invalid = || ParseAmountError::InvalidAmount(amount.to_string());
+    let whole_units: u64 = whole.parse().map_err(|_| invalid())?;
+    let fraction_units: u64 = if fraction.is_empty() {
+        0
+    } else {
+        fraction.parse().map_err(|_| invalid())?
+    };
+    let scale = 10u64.pow(decimals as u32);
+    let fraction_scale = 10u64.pow(decimals as u32 - fraction.len() as u32);
+    Ok(whole_units * scale + fraction_units * fraction_scale)
+}
+
+// The inverse of `parse_base_units`: render `amount` base units back to a right-trimmed
+// decimal string, e.g. `format_base_units(1_230_000_000, 9) == "1.23"`.
+fn format_base_units(amount: u64, decimals: u8) -> String {
+    if decimals == 0 {
+        return amount.to_string();
+    }
+    let scale = 10u64.pow(decimals as u32);
+    let whole = amount / scale;
+    let fraction = format!(
+        "{:0width$}",
+        amount % scale,
+        width = decimals as usize
+    );
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        whole.to_string()
+    } else {
+        format!("{}.{}", whole, fraction)
+    }
 }
 
 fn unique_signers(signers: Vec<&dyn Signer>) -> Vec<&dyn Signer> {
@@ -77,7 +184,8 @@ fn merge_allocations(allocations: &[Allocation]) -> Vec<Allocation> {
             .entry(&allocation.recipient)
             .or_insert(Allocation {
                 recipient: allocation.recipient.clone(),
-                amount: 0.0,
+                amount: 0,
+                lockup_date: allocation.lockup_date,
             })
             .amount += allocation.amount;
     }
@@ -89,28 +197,94 @@ fn apply_previous_transactions(
     transaction_infos: &[TransactionInfo],
 ) {
     for transaction_info in transaction_infos {
-        let mut amount = transaction_info.amount;
-        for allocation in allocations.iter_mut() {
-            if allocation.recipient != transaction_info.recipient {
-                continue;
-            }
-            if allocation.amount >= amount {
-                allocation.amount -= amount;
-                break;
-            } else {
-                amount -= allocation.amount;
-                allocation.amount = 0.0;
+        for recipient_allocation in &transaction_info.recipients {
+            let mut amount = recipient_allocation.amount;
+            for allocation in allocations.iter_mut() {
+                if allocation.recipient != recipient_allocation.recipient {
+                    continue;
+                }
+                if allocation.amount >= amount {
+                    allocation.amount -= amount;
+                    break;
+                } else {
+                    amount -= allocation.amount;
+                    allocation.amount = 0;
+                }
             }
         }
     }
-    allocations.retain(|x| x.amount > 0.5);
+    allocations.retain(|x| x.amount != 0);
 }
 
+// Convert a bid's accepted dollar amount into lamports without ever handing the dollar figure
+// itself through an `f64`: parse it directly into base units of a ten-thousandth of a dollar
+// (`BID_DOLLAR_DECIMALS` places), then scale by the dollars-per-SOL multiplier with a single
+// integer multiply-divide. `dollars_per_sol` is a market rate rather than CSV data, so it's
+// the one value here still expressed as a float.
 fn create_allocation(bid: &Bid, dollars_per_sol: f64) -> Allocation {
+    let amount_dollar_units =
+        parse_base_units(&bid.accepted_amount_dollars, BID_DOLLAR_DECIMALS).unwrap();
+    let dollar_units_per_sol =
+        (dollars_per_sol * 10u64.pow(BID_DOLLAR_DECIMALS as u32) as f64).round() as u64;
+    let amount = (amount_dollar_units as u128 * LAMPORTS_PER_SOL as u128
+        / dollar_units_per_sol as u128) as u64;
     Allocation {
         recipient: bid.primary_address.clone(),
-        amount: bid.accepted_amount_dollars / dollars_per_sol,
+        amount,
+        lockup_date: None,
+    }
+}
+
+// Fetch and unpack a mint account to find out how many decimal places its base units have.
+fn get_mint_decimals<T: Client>(client: &ThinClient<T>, mint: &Pubkey) -> Result<u8, Error> {
+    let data = client.get_account_data(mint)?;
+    Ok(Mint::unpack(&data)?.decimals)
+}
+
+// Solana executes every instruction in a transaction atomically, and packing several
+// transfers into one transaction is substantially cheaper than one transaction per
+// recipient. This is a conservative batch size that stays well under the transaction size
+// and account-lock limits for plain `system_instruction::transfer` instructions. Stake and
+// SPL-token distributions still do one recipient per transaction, since each of those
+// creates or touches accounts that are specific to a single recipient.
+const TRANSFERS_PER_TRANSACTION: usize = 10;
+
+// Plain transfers are packed `TRANSFERS_PER_TRANSACTION` to a transaction; stake and
+// SPL-token distributions stay one recipient per transaction.
+fn distribution_batch_size(args: &DistributeTokensArgs<Pubkey, Box<dyn Signer>>) -> usize {
+    if args.spl_token_args.is_none() && args.stake_args.is_none() {
+        TRANSFERS_PER_TRANSACTION
+    } else {
+        1
+    }
+}
+
+// The set of signers a single distribution transaction needs: the fee payer, the sender (or,
+// in an SPL run with a `--token-owner`, that owner in the sender's place), plus the stake
+// authorities and new stake account a stake run adds on top. Shared between
+// `distribute_tokens` (to actually sign) and `check_payer_balances` (to size the fee-payer
+// pre-flight check), so the two can't drift apart.
+fn transaction_signers<'a>(
+    args: &'a DistributeTokensArgs<Pubkey, Box<dyn Signer>>,
+    new_stake_account_keypair: &'a Keypair,
+) -> Vec<&'a dyn Signer> {
+    let mut signers: Vec<&dyn Signer> = vec![&**args.fee_payer.as_ref().unwrap()];
+    match args
+        .spl_token_args
+        .as_ref()
+        .and_then(|spl_token_args| spl_token_args.token_owner.as_ref())
+    {
+        // The token owner signs the token transfer, and the sender then appears in no
+        // instruction, so only the owner is added.
+        Some(token_owner) => signers.push(&**token_owner),
+        None => signers.push(&**args.sender_keypair.as_ref().unwrap()),
+    }
+    if let Some(stake_args) = &args.stake_args {
+        signers.push(&**stake_args.stake_authority.as_ref().unwrap());
+        signers.push(&**stake_args.withdraw_authority.as_ref().unwrap());
+        signers.push(new_stake_account_keypair);
     }
+    unique_signers(signers)
 }
 
 fn distribute_tokens<T: Client>(
@@ -118,30 +292,72 @@ fn distribute_tokens<T: Client>(
     db: &mut PickleDb,
     allocations: &[Allocation],
     args: &DistributeTokensArgs<Pubkey, Box<dyn Signer>>,
+    decimals: u8,
 ) -> Result<(), Error> {
-    for allocation in allocations {
+    let batch_size = distribution_batch_size(args);
+
+    for batch in allocations.chunks(batch_size) {
         let new_stake_account_keypair = Keypair::new();
         let new_stake_account_address = new_stake_account_keypair.pubkey();
         let signers = if args.dry_run {
             vec![]
         } else {
-            let mut signers = vec![
-                &**args.fee_payer.as_ref().unwrap(),
-                &**args.sender_keypair.as_ref().unwrap(),
-            ];
-            if let Some(stake_args) = &args.stake_args {
-                signers.push(&**stake_args.stake_authority.as_ref().unwrap());
-                signers.push(&**stake_args.withdraw_authority.as_ref().unwrap());
-                signers.push(&new_stake_account_keypair);
-            }
-            unique_signers(signers)
+            transaction_signers(args, &new_stake_account_keypair)
         };
 
-        println!("{:<44}  {:>24.9}", allocation.recipient, allocation.amount);
+        for allocation in batch {
+            println!(
+                "{:<44}  {:>24}",
+                allocation.recipient,
+                format_base_units(allocation.amount, decimals)
+            );
+        }
         let result = if args.dry_run {
             Ok(Signature::default())
         } else {
-            let instructions = if let Some(stake_args) = &args.stake_args {
+            let mut new_token_account_address = None;
+            let instructions = if let Some(spl_token_args) = &args.spl_token_args {
+                let allocation = &batch[0];
+                // The owner of the source token account defaults to the sender, but can be a
+                // distinct signer (e.g. a treasury multisig's delegate) via `--token-owner`.
+                let token_owner_pubkey = spl_token_args
+                    .token_owner
+                    .as_ref()
+                    .map(|signer| signer.pubkey())
+                    .unwrap_or_else(|| args.sender_keypair.as_ref().unwrap().pubkey());
+                let fee_payer_pubkey = args.fee_payer.as_ref().unwrap().pubkey();
+                let recipient: Pubkey = allocation.recipient.parse().unwrap();
+
+                let sender_token_address =
+                    get_associated_token_address(&token_owner_pubkey, &spl_token_args.mint);
+                let recipient_token_address =
+                    get_associated_token_address(&recipient, &spl_token_args.mint);
+                new_token_account_address = Some(recipient_token_address);
+
+                let mut instructions = vec![];
+                if client.get_account_data(&recipient_token_address)?.is_empty() {
+                    instructions.push(create_associated_token_account(
+                        &fee_payer_pubkey,
+                        &recipient,
+                        &spl_token_args.mint,
+                    ));
+                }
+                instructions.push(
+                    spl_token::instruction::transfer_checked(
+                        &spl_token::id(),
+                        &sender_token_address,
+                        &spl_token_args.mint,
+                        &recipient_token_address,
+                        &token_owner_pubkey,
+                        &[],
+                        allocation.amount,
+                        decimals,
+                    )
+                    .unwrap(),
+                );
+                instructions
+            } else if let Some(stake_args) = &args.stake_args {
+                let allocation = &batch[0];
                 let sol_for_fees = stake_args.sol_for_fees;
                 let sender_pubkey = args.sender_keypair.as_ref().unwrap().pubkey();
                 let stake_authority = stake_args.stake_authority.as_ref().unwrap().pubkey();
@@ -150,7 +366,7 @@ fn distribute_tokens<T: Client>(
                 let mut instructions = stake_instruction::split(
                     &stake_args.stake_account_address,
                     &stake_authority,
-                    sol_to_lamports(allocation.amount - sol_for_fees),
+                    allocation.amount - sol_to_lamports(sol_for_fees),
                     &new_stake_account_address,
                 );
 
@@ -178,33 +394,71 @@ fn distribute_tokens<T: Client>(
                     sol_to_lamports(sol_for_fees),
                 ));
 
+                if let Some(lockup_date) = allocation.lockup_date {
+                    instructions.push(stake_instruction::set_lockup(
+                        &new_stake_account_address,
+                        &LockupArgs {
+                            unix_timestamp: Some(lockup_date.timestamp()),
+                            epoch: None,
+                            custodian: None,
+                        },
+                        &withdraw_authority,
+                    ));
+                }
+
                 instructions
             } else {
                 let from = args.sender_keypair.as_ref().unwrap().pubkey();
-                let to = allocation.recipient.parse().unwrap();
-                let lamports = sol_to_lamports(allocation.amount);
-                let instruction = system_instruction::transfer(&from, &to, lamports);
-                vec![instruction]
+                batch
+                    .iter()
+                    .map(|allocation| {
+                        let to = allocation.recipient.parse().unwrap();
+                        system_instruction::transfer(&from, &to, allocation.amount)
+                    })
+                    .collect()
             };
 
             let fee_payer_pubkey = args.fee_payer.as_ref().unwrap().pubkey();
             let message = Message::new_with_payer(&instructions, Some(&fee_payer_pubkey));
-            let (blockhash, _fee_caluclator) = client.get_recent_blockhash()?;
+            // `last_valid_slot` lets `update_finalized_transaction` tell a transaction that
+            // is merely slow to land apart from one that has definitively expired.
+            let (blockhash, _fee_calculator, last_valid_slot) =
+                client.get_recent_blockhash_with_last_valid_slot()?;
             let transaction = Transaction::new(&signers, message, blockhash);
             let signature = transaction.signatures[0];
+            let new_account_address = if args.spl_token_args.is_some() {
+                new_token_account_address.as_ref()
+            } else if args.stake_args.is_some() {
+                Some(&new_stake_account_address)
+            } else {
+                None
+            };
             set_transaction_info(
                 db,
-                &allocation,
+                batch,
                 &signature,
                 &blockhash,
-                Some(&new_stake_account_address),
-                false,
+                last_valid_slot,
+                decimals,
+                new_account_address,
             )?;
 
-            client.async_send_transaction(transaction)
+            // Send without preflight simulation; the preflight commitment follows the run's
+            // `commitment_config`.
+            let send_config = RpcSendTransactionConfig {
+                skip_preflight: true,
+                preflight_commitment: Some(args.commitment_config.commitment),
+                ..RpcSendTransactionConfig::default()
+            };
+            client.send_transaction_with_config(transaction, send_config)
         };
         if let Err(e) = result {
-            eprintln!("Error sending tokens to {}: {}", allocation.recipient, e);
+            let recipients = batch
+                .iter()
+                .map(|allocation| allocation.recipient.as_str())
+                .collect::<Vec<_>>()
+                .join(", ");
+            eprintln!("Error sending tokens to {}: {}", recipients, e);
         }
     }
     Ok(())
@@ -226,15 +480,18 @@ fn open_db(path: &str, dry_run: bool) -> Result<PickleDb, pickledb::error::Error
 pub fn write_transaction_log<P: AsRef<Path>>(db: &PickleDb, path: &P) -> Result<(), io::Error> {
     let mut wtr = csv::WriterBuilder::new().from_path(path).unwrap();
     for (signature, info) in read_transaction_data(db) {
-        let signed_info = SignedTransactionInfo {
-            recipient: info.recipient,
-            amount: info.amount,
-            new_stake_account_address: info.new_stake_account_address,
-            finalized: info.finalized,
-            blockhash: info.blockhash,
-            signature: signature.to_string(),
-        };
-        wtr.serialize(&signed_info)?;
+        for recipient_allocation in &info.recipients {
+            let signed_info = SignedTransactionInfo {
+                recipient: recipient_allocation.recipient.clone(),
+                amount: format_base_units(recipient_allocation.amount, info.decimals),
+                new_stake_account_address: info.new_stake_account_address.clone(),
+                finalized_date: info.finalized_date.map(|date| date.to_rfc3339()),
+                blockhash: info.blockhash.clone(),
+                signature: signature.to_string(),
+                lockup_date: recipient_allocation.lockup_date.map(|date| date.to_rfc3339()),
+            };
+            wtr.serialize(&signed_info)?;
+        }
     }
     wtr.flush()
 }
@@ -258,20 +515,29 @@ fn read_transaction_infos(db: &PickleDb) -> Vec<TransactionInfo> {
 
 fn set_transaction_info(
     db: &mut PickleDb,
-    allocation: &Allocation,
+    allocations: &[Allocation],
     signature: &Signature,
     blockhash: &Hash,
+    last_valid_slot: Slot,
+    decimals: u8,
     new_stake_account_address: Option<&Pubkey>,
-    finalized: bool,
 ) -> Result<(), pickledb::error::Error> {
     let transaction_info = TransactionInfo {
-        recipient: allocation.recipient.clone(),
-        amount: allocation.amount,
+        recipients: allocations
+            .iter()
+            .map(|allocation| RecipientAllocation {
+                recipient: allocation.recipient.clone(),
+                amount: allocation.amount,
+                lockup_date: allocation.lockup_date,
+            })
+            .collect(),
+        decimals,
         new_stake_account_address: new_stake_account_address
             .map(|pubkey| pubkey.to_string())
             .unwrap_or_else(|| "".to_string()),
-        finalized,
+        finalized_date: None,
         blockhash: blockhash.to_string(),
+        last_valid_slot,
     };
     db.set(&signature.to_string(), &transaction_info)?;
     Ok(())
@@ -281,6 +547,7 @@ fn read_allocations(
     input_csv: &str,
     from_bids: bool,
     dollars_per_sol: Option<f64>,
+    decimals: u8,
 ) -> Vec<Allocation> {
     let rdr = ReaderBuilder::new().trim(Trim::All).from_path(input_csv);
     if from_bids {
@@ -289,9 +556,15 @@ fn read_allocations(
             .map(|bid| create_allocation(&bid, dollars_per_sol.unwrap()))
             .collect()
     } else {
-        rdr.unwrap()
-            .deserialize()
-            .map(|entry| entry.unwrap())
+        let entries: Vec<CsvAllocation> =
+            rdr.unwrap().deserialize().map(|entry| entry.unwrap()).collect();
+        entries
+            .into_iter()
+            .map(|entry| Allocation {
+                recipient: entry.recipient,
+                amount: parse_base_units(&entry.amount, decimals).unwrap(),
+                lockup_date: entry.lockup_date.map(|date| date.parse().unwrap()),
+            })
             .collect()
     }
 }
@@ -304,29 +577,162 @@ fn new_spinner_progress_bar() -> ProgressBar {
     progress_bar
 }
 
+// Work out how many lamports the sender and fee-payer accounts need to hold before a run
+// starts. The sender covers the allocations themselves (or, in stake mode, the per-recipient
+// `sol_for_fees` top-up transferred alongside the stake delegation), while the fee-payer covers
+// every transaction's signature fee plus the rent-exempt minimum for any accounts the run will
+// create. Returns `(sender_required, fee_payer_required)`.
+fn compute_required_lamports(
+    num_transactions: u64,
+    num_signers: u64,
+    lamports_per_signature: u64,
+    undistributed_tokens: u64,
+    sol_for_fees: Option<f64>,
+    is_spl: bool,
+    new_account_rent_exempt_minimum: u64,
+) -> (u64, u64) {
+    let fee_payer_required = lamports_per_signature * num_signers * num_transactions
+        + new_account_rent_exempt_minimum * num_transactions;
+    let sender_required = match sol_for_fees {
+        Some(sol_for_fees) => sol_to_lamports(sol_for_fees) * num_transactions,
+        // An SPL distribution spends no lamports out of the sender -- `undistributed_tokens`
+        // is denominated in the mint's base units, not lamports, and is checked separately
+        // against the sender's token account balance below.
+        None if is_spl => 0,
+        None => undistributed_tokens,
+    };
+    (sender_required, fee_payer_required)
+}
+
+// Make sure the sender and fee-payer accounts can actually afford the run before submitting a
+// single transaction, rather than failing partway through.
+fn check_payer_balances<T: Client>(
+    client: &ThinClient<T>,
+    num_transactions: u64,
+    undistributed_tokens: u64,
+    args: &DistributeTokensArgs<Pubkey, Box<dyn Signer>>,
+) -> Result<(), Error> {
+    let (_blockhash, fee_calculator) =
+        client.get_recent_blockhash_with_commitment(args.commitment_config)?;
+    let new_account_rent_exempt_minimum = if args.stake_args.is_some() {
+        Rent::default().minimum_balance(std::mem::size_of::<StakeState>())
+    } else if args.spl_token_args.is_some() {
+        Rent::default().minimum_balance(spl_token::state::Account::LEN)
+    } else {
+        0
+    };
+    // Count signers the same way `distribute_tokens` builds its signer list, so a stake or
+    // SPL run with extra authorities isn't under-counted against a bare "2" (fee payer +
+    // sender).
+    let num_signers = transaction_signers(args, &Keypair::new()).len() as u64;
+    let (sender_required, fee_payer_required) = compute_required_lamports(
+        num_transactions,
+        num_signers,
+        fee_calculator.lamports_per_signature,
+        undistributed_tokens,
+        args.stake_args.as_ref().map(|stake_args| stake_args.sol_for_fees),
+        args.spl_token_args.is_some(),
+        new_account_rent_exempt_minimum,
+    );
+
+    let sender_pubkey = args.sender_keypair.as_ref().unwrap().pubkey();
+    let fee_payer_pubkey = args.fee_payer.as_ref().unwrap().pubkey();
+
+    if sender_pubkey == fee_payer_pubkey {
+        let required = sender_required + fee_payer_required;
+        let balance =
+            client.get_balance_with_commitment(&sender_pubkey, args.commitment_config)?;
+        if balance < required {
+            return Err(Error::InsufficientBalance(format!(
+                "Error: {} has ◎{}, but the distribution needs ◎{}",
+                sender_pubkey,
+                lamports_to_sol(balance),
+                lamports_to_sol(required),
+            )));
+        }
+    } else {
+        let sender_balance =
+            client.get_balance_with_commitment(&sender_pubkey, args.commitment_config)?;
+        if sender_balance < sender_required {
+            return Err(Error::InsufficientBalance(format!(
+                "Error: sender {} has ◎{}, but the distribution needs ◎{}",
+                sender_pubkey,
+                lamports_to_sol(sender_balance),
+                lamports_to_sol(sender_required),
+            )));
+        }
+
+        let fee_payer_balance =
+            client.get_balance_with_commitment(&fee_payer_pubkey, args.commitment_config)?;
+        if fee_payer_balance < fee_payer_required {
+            return Err(Error::InsufficientBalance(format!(
+                "Error: fee payer {} has ◎{}, but the transactions need ◎{}",
+                fee_payer_pubkey,
+                lamports_to_sol(fee_payer_balance),
+                lamports_to_sol(fee_payer_required),
+            )));
+        }
+    }
+
+    // Lamports cover fees and rent, but an SPL distribution draws the distributed amount
+    // itself out of a token account, not the sender's native balance -- check that
+    // separately, in the mint's own base units.
+    if let Some(spl_token_args) = &args.spl_token_args {
+        let token_owner_pubkey = spl_token_args
+            .token_owner
+            .as_ref()
+            .map(|signer| signer.pubkey())
+            .unwrap_or(sender_pubkey);
+        let sender_token_address =
+            get_associated_token_address(&token_owner_pubkey, &spl_token_args.mint);
+        let data = client.get_account_data(&sender_token_address)?;
+        let token_balance = if data.is_empty() {
+            0
+        } else {
+            spl_token::state::Account::unpack(&data)?.amount
+        };
+        if token_balance < undistributed_tokens {
+            let decimals = get_mint_decimals(client, &spl_token_args.mint)?;
+            return Err(Error::InsufficientBalance(format!(
+                "Error: token account {} has {}, but the distribution needs {}",
+                sender_token_address,
+                format_base_units(token_balance, decimals),
+                format_base_units(undistributed_tokens, decimals),
+            )));
+        }
+    }
+
+    Ok(())
+}
+
 pub fn process_distribute_tokens<T: Client>(
     client: &ThinClient<T>,
     args: &DistributeTokensArgs<Pubkey, Box<dyn Signer>>,
 ) -> Result<Option<usize>, Error> {
+    let decimals = match &args.spl_token_args {
+        Some(spl_token_args) => get_mint_decimals(client, &spl_token_args.mint)?,
+        None => SOL_DECIMALS,
+    };
+
     let mut allocations: Vec<Allocation> =
-        read_allocations(&args.input_csv, args.from_bids, args.dollars_per_sol);
+        read_allocations(&args.input_csv, args.from_bids, args.dollars_per_sol, decimals);
 
-    let starting_total_tokens: f64 = allocations.iter().map(|x| x.amount).sum();
+    let starting_total_tokens: u64 = allocations.iter().map(|x| x.amount).sum();
     println!(
         "{} ◎{}",
         style("Total in input_csv:").bold(),
-        starting_total_tokens,
+        format_base_units(starting_total_tokens, decimals),
     );
     if let Some(dollars_per_sol) = args.dollars_per_sol {
         println!(
             "{} ${}",
             style("Total in input_csv:").bold(),
-            starting_total_tokens * dollars_per_sol,
+            lamports_to_sol(starting_total_tokens) * dollars_per_sol,
         );
     }
 
     let mut db = open_db(&args.transactions_db, args.dry_run)?;
-    let confirmations = update_finalized_transactions(client, &mut db)?;
+    let confirmations = update_finalized_transactions(client, &mut db, args.commitment_config)?;
     if confirmations.is_some() {
         eprintln!("warning: unfinalized transactions");
     }
@@ -347,13 +753,27 @@ pub fn process_distribute_tokens<T: Client>(
     //  3. The recipient correctly got tokens in a previous run, and then later registered the same
     //     address for another bid. If so, update this code to check for that case.
     for allocation in &allocations {
-        let address = allocation.recipient.parse().unwrap();
-        let balance = client.get_balance(&address).unwrap();
+        let address: Pubkey = allocation.recipient.parse().unwrap();
+        // For an SPL distribution, the recipient's *token* balance is what matters here, not
+        // their native SOL balance -- an ordinary wallet holding SOL shouldn't trip this guard.
+        let balance = if let Some(spl_token_args) = &args.spl_token_args {
+            let token_address = get_associated_token_address(&address, &spl_token_args.mint);
+            let data = client.get_account_data(&token_address).unwrap();
+            if data.is_empty() {
+                0
+            } else {
+                spl_token::state::Account::unpack(&data).unwrap().amount
+            }
+        } else {
+            client
+                .get_balance_with_commitment(&address, args.commitment_config)
+                .unwrap()
+        };
         if args.stake_args.is_none() && !args.force && balance != 0 {
             eprintln!(
                 "Error: Non-zero balance {}, refusing to send {} to {}",
                 lamports_to_sol(balance),
-                allocation.amount,
+                format_base_units(allocation.amount, decimals),
                 allocation.recipient,
             );
             process::exit(1);
@@ -369,44 +789,57 @@ pub fn process_distribute_tokens<T: Client>(
         .bold()
     );
 
-    let distributed_tokens: f64 = transaction_infos.iter().map(|x| x.amount).sum();
-    let undistributed_tokens: f64 = allocations.iter().map(|x| x.amount).sum();
-    println!("{} ◎{}", style("Distributed:").bold(), distributed_tokens,);
+    let distributed_tokens: u64 = transaction_infos
+        .iter()
+        .flat_map(|info| &info.recipients)
+        .map(|recipient_allocation| recipient_allocation.amount)
+        .sum();
+    let undistributed_tokens: u64 = allocations.iter().map(|x| x.amount).sum();
+    println!(
+        "{} ◎{}",
+        style("Distributed:").bold(),
+        format_base_units(distributed_tokens, decimals),
+    );
     if let Some(dollars_per_sol) = args.dollars_per_sol {
         println!(
             "{} ${}",
             style("Distributed:").bold(),
-            distributed_tokens * dollars_per_sol,
+            lamports_to_sol(distributed_tokens) * dollars_per_sol,
         );
     }
     println!(
         "{} ◎{}",
         style("Undistributed:").bold(),
-        undistributed_tokens,
+        format_base_units(undistributed_tokens, decimals),
     );
     if let Some(dollars_per_sol) = args.dollars_per_sol {
         println!(
             "{} ${}",
             style("Undistributed:").bold(),
-            undistributed_tokens * dollars_per_sol,
+            lamports_to_sol(undistributed_tokens) * dollars_per_sol,
         );
     }
     println!(
         "{} ◎{}",
         style("Total:").bold(),
-        distributed_tokens + undistributed_tokens,
+        format_base_units(distributed_tokens + undistributed_tokens, decimals),
     );
     if let Some(dollars_per_sol) = args.dollars_per_sol {
         println!(
             "{} ${}",
             style("Total:").bold(),
-            (distributed_tokens + undistributed_tokens) * dollars_per_sol,
+            lamports_to_sol(distributed_tokens + undistributed_tokens) * dollars_per_sol,
         );
     }
 
-    distribute_tokens(client, &mut db, &allocations, args)?;
+    let batch_size = distribution_batch_size(args) as u64;
+    let num_transactions = (allocations.len() as u64 + batch_size - 1) / batch_size;
+    check_payer_balances(client, num_transactions, undistributed_tokens, args)?;
+
+    distribute_tokens(client, &mut db, &allocations, args, decimals)?;
 
-    let mut opt_confirmations = update_finalized_transactions(client, &mut db)?;
+    let mut opt_confirmations =
+        update_finalized_transactions(client, &mut db, args.commitment_config)?;
 
     if args.no_wait {
         return Ok(opt_confirmations);
@@ -423,23 +856,28 @@ pub fn process_distribute_tokens<T: Client>(
 
         // Sleep for about 1 slot
         sleep(Duration::from_millis(500));
-        opt_confirmations = update_finalized_transactions(client, &mut db)?;
+        opt_confirmations = update_finalized_transactions(client, &mut db, args.commitment_config)?;
     }
     Ok(opt_confirmations)
 }
 
-// Set the finalized bit in the database if the transaction is rooted.
+// Set `finalized_date` in the database once the transaction reaches `commitment_config`
+// (rooted, at `finalized`).
 // Remove the TransactionInfo from the database if the transaction failed.
 // Return the number of confirmations on the transaction or None if finalized.
 fn update_finalized_transaction(
     db: &mut PickleDb,
     signature: &Signature,
     opt_transaction_status: Option<TransactionStatus>,
-    blockhash: &Hash,
-    recent_blockhashes: &[Hash],
+    last_valid_slot: Slot,
+    root_slot: Slot,
+    commitment_config: CommitmentConfig,
 ) -> Result<Option<usize>, pickledb::error::Error> {
     if opt_transaction_status.is_none() {
-        if !recent_blockhashes.contains(blockhash) {
+        // The signature isn't visible yet. It can only be declared dead once the root slot
+        // has passed `last_valid_slot` -- at that point its blockhash can never be accepted
+        // again, so there's no risk of the transaction still landing later.
+        if root_slot > last_valid_slot {
             eprintln!("Signature not found {} and blockhash expired", signature);
             println!("Discarding transaction record");
             db.rem(&signature.to_string())?;
@@ -452,9 +890,14 @@ fn update_finalized_transaction(
     }
     let transaction_status = opt_transaction_status.unwrap();
 
-    if let Some(confirmations) = transaction_status.confirmations {
-        // The transaction was found but is not yet finalized.
-        return Ok(Some(confirmations));
+    // At `finalized` commitment, wait for the transaction to be rooted (`confirmations ==
+    // None`). A caller that only asked for `confirmed` or weaker is satisfied as soon as the
+    // transaction lands with any status at all.
+    if commitment_config.commitment == CommitmentLevel::Finalized {
+        if let Some(confirmations) = transaction_status.confirmations {
+            // The transaction was found but is not yet finalized.
+            return Ok(Some(confirmations));
+        }
     }
 
     if let Err(e) = &transaction_status.status {
@@ -469,48 +912,54 @@ fn update_finalized_transaction(
         return Ok(None);
     }
 
-    // Transaction is rooted. Set finalized in the database.
+    // Transaction has reached the requested commitment. Record when it was observed to.
     let mut transaction_info = db.get::<TransactionInfo>(&signature.to_string()).unwrap();
-    transaction_info.finalized = true;
+    transaction_info.finalized_date = Some(Utc::now());
     db.set(&signature.to_string(), &transaction_info)?;
     Ok(None)
 }
 
-// Update the finalized bit on any transactions that are now rooted
+// Update `finalized_date` on any transactions that have now reached `commitment_config`.
 // Return the lowest number of confirmations on the unfinalized transactions or None if all are finalized.
 fn update_finalized_transactions<T: Client>(
     client: &ThinClient<T>,
     db: &mut PickleDb,
+    commitment_config: CommitmentConfig,
 ) -> Result<Option<usize>, Error> {
     let transaction_data = read_transaction_data(db);
-    let unconfirmed_signatures_and_blockhashes: Vec<_> = transaction_data
+    let unconfirmed_signatures_and_last_valid_slots: Vec<_> = transaction_data
         .iter()
         .filter_map(|(signature, info)| {
-            if info.finalized {
+            if info.finalized_date.is_some() {
                 None
             } else {
-                Some((*signature, info.blockhash.parse().unwrap()))
+                Some((*signature, info.last_valid_slot))
             }
         })
         .collect();
-    let unconfirmed_signatures = unconfirmed_signatures_and_blockhashes
+    let unconfirmed_signatures = unconfirmed_signatures_and_last_valid_slots
         .iter()
         .map(|(sig, _)| *sig)
         .collect_vec();
+    // `ThinClient::get_signature_statuses` already chunks the request at
+    // `MAX_GET_SIGNATURE_STATUSES_QUERY_ITEMS` and concatenates the results in input order,
+    // so a single call here is safe no matter how many transactions are outstanding.
     let transaction_statuses = client.get_signature_statuses(&unconfirmed_signatures)?;
-    let recent_blockhashes = client.get_recent_blockhashes()?;
+    let root_slot = client.get_slot()?;
 
     let mut confirmations = None;
-    for ((signature, blockhash), opt_transaction_status) in unconfirmed_signatures_and_blockhashes
-        .into_iter()
-        .zip(transaction_statuses.into_iter())
+    for ((signature, last_valid_slot), opt_transaction_status) in
+        unconfirmed_signatures_and_last_valid_slots
+            .into_iter()
+            .zip(transaction_statuses.into_iter())
     {
         if let Some(confs) = update_finalized_transaction(
             db,
             &signature,
             opt_transaction_status,
-            &blockhash,
-            &recent_blockhashes,
+            last_valid_slot,
+            root_slot,
+            commitment_config,
         )? {
             confirmations = Some(cmp::min(confs, confirmations.unwrap_or(usize::MAX)));
         }
@@ -521,9 +970,14 @@ fn update_finalized_transactions<T: Client>(
 pub fn process_balances<T: Client>(
     client: &ThinClient<T>,
     args: &BalancesArgs,
-) -> Result<(), csv::Error> {
+) -> Result<(), Error> {
+    let decimals = match &args.spl_token_args {
+        Some(spl_token_args) => get_mint_decimals(client, &spl_token_args.mint)?,
+        None => SOL_DECIMALS,
+    };
+
     let allocations: Vec<Allocation> =
-        read_allocations(&args.input_csv, args.from_bids, args.dollars_per_sol);
+        read_allocations(&args.input_csv, args.from_bids, args.dollars_per_sol, decimals);
     let allocations = merge_allocations(&allocations);
 
     println!(
@@ -535,16 +989,41 @@ pub fn process_balances<T: Client>(
         .bold()
     );
 
-    for allocation in &allocations {
-        let address = allocation.recipient.parse().unwrap();
-        let expected = lamports_to_sol(sol_to_lamports(allocation.amount));
-        let actual = lamports_to_sol(client.get_balance(&address).unwrap());
+    // Look up every recipient's balance in as few RPC round-trips as the server's
+    // multiple-accounts batch limit allows, rather than one request per recipient -- a CSV
+    // with thousands of recipients would otherwise issue thousands of single requests.
+    let query_addresses: Vec<Pubkey> = allocations
+        .iter()
+        .map(|allocation| {
+            let address: Pubkey = allocation.recipient.parse().unwrap();
+            match &args.spl_token_args {
+                Some(spl_token_args) => get_associated_token_address(&address, &spl_token_args.mint),
+                None => address,
+            }
+        })
+        .collect();
+    let accounts =
+        client.get_multiple_accounts_with_commitment(&query_addresses, args.commitment_config)?;
+
+    for (allocation, account) in allocations.iter().zip(accounts.into_iter()) {
+        let actual = match (&args.spl_token_args, account) {
+            (Some(_), Some(account)) => spl_token::state::Account::unpack(&account.data)?.amount,
+            (Some(_), None) => 0,
+            (None, Some(account)) => account.lamports,
+            (None, None) => 0,
+        };
+        let diff = actual as i64 - allocation.amount as i64;
+        let diff_str = format!(
+            "{}{}",
+            if diff < 0 { "-" } else { "" },
+            format_base_units(diff.abs() as u64, decimals)
+        );
         println!(
-            "{:<44}  {:>24.9}  {:>24.9}  {:>24.9}",
+            "{:<44}  {:>24}  {:>24}  {:>24}",
             allocation.recipient,
-            expected,
-            actual,
-            actual - expected
+            format_base_units(allocation.amount, decimals),
+            format_base_units(actual, decimals),
+            diff_str,
         );
     }
 
@@ -569,12 +1048,18 @@ pub fn test_process_distribute_tokens_with_client<C: Client>(client: C, sender_k
     let alice_pubkey = Pubkey::new_rand();
     let allocation = Allocation {
         recipient: alice_pubkey.to_string(),
-        amount: 1000.0,
+        amount: sol_to_lamports(1000.0),
+        lockup_date: None,
     };
     let allocations_file = NamedTempFile::new().unwrap();
     let input_csv = allocations_file.path().to_str().unwrap().to_string();
     let mut wtr = csv::WriterBuilder::new().from_writer(allocations_file);
-    wtr.serialize(&allocation).unwrap();
+    wtr.serialize(&CsvAllocation {
+        recipient: allocation.recipient.clone(),
+        amount: "1000".to_string(),
+        lockup_date: None,
+    })
+    .unwrap();
     wtr.flush().unwrap();
 
     let dir = tempdir().unwrap();
@@ -596,18 +1081,20 @@ pub fn test_process_distribute_tokens_with_client<C: Client>(client: C, sender_k
         dollars_per_sol: None,
         force: false,
         stake_args: None,
+        spl_token_args: None,
+        commitment_config: CommitmentConfig::default(),
     };
     let confirmations = process_distribute_tokens(&thin_client, &args).unwrap();
     assert_eq!(confirmations, None);
 
     let transaction_infos = read_transaction_infos(&open_db(&transactions_db, true).unwrap());
     assert_eq!(transaction_infos.len(), 1);
-    assert_eq!(transaction_infos[0].recipient, alice_pubkey.to_string());
-    let expected_amount = sol_to_lamports(allocation.amount);
     assert_eq!(
-        sol_to_lamports(transaction_infos[0].amount),
-        expected_amount
+        transaction_infos[0].recipients[0].recipient,
+        alice_pubkey.to_string()
     );
+    let expected_amount = allocation.amount;
+    assert_eq!(transaction_infos[0].recipients[0].amount, expected_amount);
 
     assert_eq!(
         thin_client.get_balance(&alice_pubkey).unwrap(),
@@ -618,12 +1105,12 @@ pub fn test_process_distribute_tokens_with_client<C: Client>(client: C, sender_k
     process_distribute_tokens(&thin_client, &args).unwrap();
     let transaction_infos = read_transaction_infos(&open_db(&transactions_db, true).unwrap());
     assert_eq!(transaction_infos.len(), 1);
-    assert_eq!(transaction_infos[0].recipient, alice_pubkey.to_string());
-    let expected_amount = sol_to_lamports(allocation.amount);
     assert_eq!(
-        sol_to_lamports(transaction_infos[0].amount),
-        expected_amount
+        transaction_infos[0].recipients[0].recipient,
+        alice_pubkey.to_string()
     );
+    let expected_amount = allocation.amount;
+    assert_eq!(transaction_infos[0].recipients[0].amount, expected_amount);
 
     assert_eq!(
         thin_client.get_balance(&alice_pubkey).unwrap(),
@@ -656,18 +1143,24 @@ pub fn test_process_distribute_stake_with_client<C: Client>(client: C, sender_ke
         sol_to_lamports(3000.0),
     );
     let message = Message::new(&instructions);
-    let signers = [&sender_keypair, &stake_account_keypair];
+    let signers: [&dyn Signer; 2] = [&sender_keypair, &stake_account_keypair];
     thin_client.send_message(message, &signers).unwrap();
 
     let alice_pubkey = Pubkey::new_rand();
     let allocation = Allocation {
         recipient: alice_pubkey.to_string(),
-        amount: 1000.0,
+        amount: sol_to_lamports(1000.0),
+        lockup_date: None,
     };
     let file = NamedTempFile::new().unwrap();
     let input_csv = file.path().to_str().unwrap().to_string();
     let mut wtr = csv::WriterBuilder::new().from_writer(file);
-    wtr.serialize(&allocation).unwrap();
+    wtr.serialize(&CsvAllocation {
+        recipient: allocation.recipient.clone(),
+        amount: "1000".to_string(),
+        lockup_date: None,
+    })
+    .unwrap();
     wtr.flush().unwrap();
 
     let dir = tempdir().unwrap();
@@ -695,18 +1188,20 @@ pub fn test_process_distribute_stake_with_client<C: Client>(client: C, sender_ke
         from_bids: false,
         sender_keypair: Some(Box::new(sender_keypair)),
         dollars_per_sol: None,
+        spl_token_args: None,
+        commitment_config: CommitmentConfig::default(),
     };
     let confirmations = process_distribute_tokens(&thin_client, &args).unwrap();
     assert_eq!(confirmations, None);
 
     let transaction_infos = read_transaction_infos(&open_db(&transactions_db, true).unwrap());
     assert_eq!(transaction_infos.len(), 1);
-    assert_eq!(transaction_infos[0].recipient, alice_pubkey.to_string());
-    let expected_amount = sol_to_lamports(allocation.amount);
     assert_eq!(
-        sol_to_lamports(transaction_infos[0].amount),
-        expected_amount
+        transaction_infos[0].recipients[0].recipient,
+        alice_pubkey.to_string()
     );
+    let expected_amount = allocation.amount;
+    assert_eq!(transaction_infos[0].recipients[0].amount, expected_amount);
 
     assert_eq!(
         thin_client.get_balance(&alice_pubkey).unwrap(),
@@ -725,12 +1220,12 @@ pub fn test_process_distribute_stake_with_client<C: Client>(client: C, sender_ke
     process_distribute_tokens(&thin_client, &args).unwrap();
     let transaction_infos = read_transaction_infos(&open_db(&transactions_db, true).unwrap());
     assert_eq!(transaction_infos.len(), 1);
-    assert_eq!(transaction_infos[0].recipient, alice_pubkey.to_string());
-    let expected_amount = sol_to_lamports(allocation.amount);
     assert_eq!(
-        sol_to_lamports(transaction_infos[0].amount),
-        expected_amount
+        transaction_infos[0].recipients[0].recipient,
+        alice_pubkey.to_string()
     );
+    let expected_amount = allocation.amount;
+    assert_eq!(transaction_infos[0].recipients[0].amount, expected_amount);
 
     assert_eq!(
         thin_client.get_balance(&alice_pubkey).unwrap(),
@@ -742,6 +1237,184 @@ pub fn test_process_distribute_stake_with_client<C: Client>(client: C, sender_ke
     );
 }
 
+// An underfunded fee payer should abort the whole run via `check_payer_balances`, before a
+// single transaction is sent, rather than dying partway through.
+pub fn test_process_distribute_tokens_insufficient_balance_with_client<C: Client>(
+    client: C,
+    sender_keypair: Keypair,
+) {
+    let thin_client = ThinClient(client);
+    // Leave the fee payer unfunded, unlike the other `_with_client` tests above.
+    let fee_payer = Keypair::new();
+
+    let alice_pubkey = Pubkey::new_rand();
+    let allocation = Allocation {
+        recipient: alice_pubkey.to_string(),
+        amount: sol_to_lamports(1000.0),
+        lockup_date: None,
+    };
+    let allocations_file = NamedTempFile::new().unwrap();
+    let input_csv = allocations_file.path().to_str().unwrap().to_string();
+    let mut wtr = csv::WriterBuilder::new().from_writer(allocations_file);
+    wtr.serialize(&CsvAllocation {
+        recipient: allocation.recipient.clone(),
+        amount: "1000".to_string(),
+        lockup_date: None,
+    })
+    .unwrap();
+    wtr.flush().unwrap();
+
+    let dir = tempdir().unwrap();
+    let transactions_db = dir
+        .path()
+        .join("transactions.db")
+        .to_str()
+        .unwrap()
+        .to_string();
+
+    let args: DistributeTokensArgs<Pubkey, Box<dyn Signer>> = DistributeTokensArgs {
+        sender_keypair: Some(Box::new(sender_keypair)),
+        fee_payer: Some(Box::new(fee_payer)),
+        dry_run: false,
+        no_wait: false,
+        input_csv,
+        from_bids: false,
+        transactions_db,
+        dollars_per_sol: None,
+        force: false,
+        stake_args: None,
+        spl_token_args: None,
+        commitment_config: CommitmentConfig::default(),
+    };
+    match process_distribute_tokens(&thin_client, &args) {
+        Err(Error::InsufficientBalance(_)) => (),
+        other => panic!("expected Error::InsufficientBalance, got {:?}", other),
+    }
+
+    // Nothing should have been sent, so the recipient's balance stays untouched.
+    assert_eq!(thin_client.get_balance(&alice_pubkey).unwrap(), 0);
+}
+
+pub fn test_process_distribute_tokens_spl_with_client<C: Client>(client: C, sender_keypair: Keypair) {
+    let thin_client = ThinClient(client);
+    let fee_payer = Keypair::new();
+    thin_client
+        .transfer(sol_to_lamports(1.0), &sender_keypair, &fee_payer.pubkey())
+        .unwrap();
+
+    let sender_pubkey = sender_keypair.pubkey();
+    let decimals = 6;
+    let mint_keypair = Keypair::new();
+    let mint_pubkey = mint_keypair.pubkey();
+    let create_mint_instructions = vec![
+        system_instruction::create_account(
+            &sender_pubkey,
+            &mint_pubkey,
+            Rent::default().minimum_balance(spl_token::state::Mint::LEN),
+            spl_token::state::Mint::LEN as u64,
+            &spl_token::id(),
+        ),
+        spl_token::instruction::initialize_mint(
+            &spl_token::id(),
+            &mint_pubkey,
+            &sender_pubkey,
+            None,
+            decimals,
+        )
+        .unwrap(),
+    ];
+    let message = Message::new(&create_mint_instructions);
+    let signers: [&dyn Signer; 2] = [&sender_keypair, &mint_keypair];
+    thin_client.send_message(message, &signers).unwrap();
+
+    // Create and fund the sender's own associated token account, the same way `distribute_tokens`
+    // creates one for each recipient that doesn't already have one.
+    let sender_token_address = get_associated_token_address(&sender_pubkey, &mint_pubkey);
+    let create_sender_ata_instruction =
+        create_associated_token_account(&fee_payer.pubkey(), &sender_pubkey, &mint_pubkey);
+    let message = Message::new(&[create_sender_ata_instruction]);
+    thin_client.send_message(message, &[&fee_payer]).unwrap();
+
+    let total_tokens = 5_000 * 10u64.pow(decimals as u32);
+    let mint_to_instruction = spl_token::instruction::mint_to(
+        &spl_token::id(),
+        &mint_pubkey,
+        &sender_token_address,
+        &sender_pubkey,
+        &[],
+        total_tokens,
+    )
+    .unwrap();
+    let message = Message::new(&[mint_to_instruction]);
+    thin_client.send_message(message, &[&sender_keypair]).unwrap();
+
+    let alice_pubkey = Pubkey::new_rand();
+    let allocation = Allocation {
+        recipient: alice_pubkey.to_string(),
+        amount: 1000 * 10u64.pow(decimals as u32),
+        lockup_date: None,
+    };
+    let allocations_file = NamedTempFile::new().unwrap();
+    let input_csv = allocations_file.path().to_str().unwrap().to_string();
+    let mut wtr = csv::WriterBuilder::new().from_writer(allocations_file);
+    wtr.serialize(&CsvAllocation {
+        recipient: allocation.recipient.clone(),
+        amount: "1000".to_string(),
+        lockup_date: None,
+    })
+    .unwrap();
+    wtr.flush().unwrap();
+
+    let dir = tempdir().unwrap();
+    let transactions_db = dir
+        .path()
+        .join("transactions.db")
+        .to_str()
+        .unwrap()
+        .to_string();
+
+    let spl_token_args: SplTokenArgs<Pubkey, Box<dyn Signer>> = SplTokenArgs {
+        mint: mint_pubkey,
+        token_owner: None,
+    };
+    let args: DistributeTokensArgs<Pubkey, Box<dyn Signer>> = DistributeTokensArgs {
+        sender_keypair: Some(Box::new(sender_keypair)),
+        fee_payer: Some(Box::new(fee_payer)),
+        dry_run: false,
+        no_wait: false,
+        input_csv,
+        from_bids: false,
+        transactions_db: transactions_db.clone(),
+        dollars_per_sol: None,
+        force: false,
+        stake_args: None,
+        spl_token_args: Some(spl_token_args),
+        commitment_config: CommitmentConfig::default(),
+    };
+    let confirmations = process_distribute_tokens(&thin_client, &args).unwrap();
+    assert_eq!(confirmations, None);
+
+    let transaction_infos = read_transaction_infos(&open_db(&transactions_db, true).unwrap());
+    assert_eq!(transaction_infos.len(), 1);
+    assert_eq!(
+        transaction_infos[0].recipients[0].recipient,
+        alice_pubkey.to_string()
+    );
+    let expected_amount = allocation.amount;
+    assert_eq!(transaction_infos[0].recipients[0].amount, expected_amount);
+
+    let recipient_token_address = get_associated_token_address(&alice_pubkey, &mint_pubkey);
+    let data = thin_client.get_account_data(&recipient_token_address).unwrap();
+    let recipient_token_balance = spl_token::state::Account::unpack(&data).unwrap().amount;
+    assert_eq!(recipient_token_balance, expected_amount);
+
+    // Now, run it again, and check there's no double-spend.
+    process_distribute_tokens(&thin_client, &args).unwrap();
+    let data = thin_client.get_account_data(&recipient_token_address).unwrap();
+    let recipient_token_balance = spl_token::state::Account::unpack(&data).unwrap().amount;
+    assert_eq!(recipient_token_balance, expected_amount);
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -764,20 +1437,127 @@ mod tests {
         test_process_distribute_stake_with_client(bank_client, sender_keypair);
     }
 
+    #[test]
+    fn test_process_distribute_tokens_insufficient_balance() {
+        let (genesis_config, sender_keypair) = create_genesis_config(sol_to_lamports(9_000_000.0));
+        let bank = Bank::new(&genesis_config);
+        let bank_client = BankClient::new(bank);
+        test_process_distribute_tokens_insufficient_balance_with_client(bank_client, sender_keypair);
+    }
+
+    #[test]
+    fn test_process_distribute_tokens_spl() {
+        let (genesis_config, sender_keypair) = create_genesis_config(sol_to_lamports(9_000_000.0));
+        let mut bank = Bank::new(&genesis_config);
+        // `spl_token` and `spl_associated_token_account` aren't native Solana programs, so a
+        // `BankClient`-backed bank needs them registered as builtins to execute their
+        // instructions in-process, the same way a real cluster would load them from chain.
+        bank.add_builtin(
+            "spl_token",
+            spl_token::id(),
+            spl_token::processor::Processor::process,
+        );
+        bank.add_builtin(
+            "spl_associated_token_account",
+            spl_associated_token_account::id(),
+            spl_associated_token_account::processor::process_instruction,
+        );
+        let bank_client = BankClient::new(bank);
+        test_process_distribute_tokens_spl_with_client(bank_client, sender_keypair);
+    }
+
+    #[test]
+    fn test_compute_required_lamports_transfer_mode() {
+        let (sender_required, fee_payer_required) =
+            compute_required_lamports(10, 2, 5_000, 1_000_000, None, false, 0);
+        assert_eq!(sender_required, 1_000_000);
+        assert_eq!(fee_payer_required, 5_000 * 2 * 10);
+    }
+
+    #[test]
+    fn test_compute_required_lamports_stake_mode() {
+        let (sender_required, fee_payer_required) =
+            compute_required_lamports(4, 5, 5_000, 0, Some(1.0), false, 2_000_000);
+        assert_eq!(sender_required, sol_to_lamports(1.0) * 4);
+        assert_eq!(fee_payer_required, 5_000 * 5 * 4 + 2_000_000 * 4);
+    }
+
+    #[test]
+    fn test_compute_required_lamports_spl_mode() {
+        // An SPL run spends no lamports out of the sender for the distributed amount itself
+        // -- that's checked separately against the sender's token account balance.
+        let (sender_required, fee_payer_required) =
+            compute_required_lamports(4, 3, 5_000, 1_000_000_000, None, true, 2_000_000);
+        assert_eq!(sender_required, 0);
+        assert_eq!(fee_payer_required, 5_000 * 3 * 4 + 2_000_000 * 4);
+    }
+
+    #[test]
+    fn test_parse_base_units() {
+        assert_eq!(parse_base_units("42", 9).unwrap(), 42_000_000_000);
+        assert_eq!(parse_base_units("1.5", 9).unwrap(), 1_500_000_000);
+        assert_eq!(parse_base_units("0.000000001", 9).unwrap(), 1);
+        assert!(parse_base_units("0.0000000001", 9).is_err());
+        assert!(parse_base_units("abc", 9).is_err());
+    }
+
+    #[test]
+    fn test_format_base_units() {
+        assert_eq!(format_base_units(42_000_000_000, 9), "42");
+        assert_eq!(format_base_units(1_500_000_000, 9), "1.5");
+        assert_eq!(format_base_units(1, 9), "0.000000001");
+        assert_eq!(format_base_units(42, 0), "42");
+    }
+
     #[test]
     fn test_read_allocations() {
         let alice_pubkey = Pubkey::new_rand();
         let allocation = Allocation {
             recipient: alice_pubkey.to_string(),
-            amount: 42.0,
+            amount: sol_to_lamports(42.0),
+            lockup_date: None,
         };
         let file = NamedTempFile::new().unwrap();
         let input_csv = file.path().to_str().unwrap().to_string();
         let mut wtr = csv::WriterBuilder::new().from_writer(file);
-        wtr.serialize(&allocation).unwrap();
+        wtr.serialize(&CsvAllocation {
+            recipient: alice_pubkey.to_string(),
+            amount: "42".to_string(),
+            lockup_date: None,
+        })
+        .unwrap();
         wtr.flush().unwrap();
 
-        assert_eq!(read_allocations(&input_csv, false, None), vec![allocation]);
+        assert_eq!(
+            read_allocations(&input_csv, false, None, 9),
+            vec![allocation]
+        );
+    }
+
+    #[test]
+    fn test_read_allocations_with_lockup_date() {
+        let alice_pubkey = Pubkey::new_rand();
+        let lockup_date = "2021-01-07T00:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let allocation = Allocation {
+            recipient: alice_pubkey.to_string(),
+            amount: sol_to_lamports(42.0),
+            lockup_date: Some(lockup_date),
+        };
+        let file = NamedTempFile::new().unwrap();
+        let input_csv = file.path().to_str().unwrap().to_string();
+        let mut wtr = csv::WriterBuilder::new().from_writer(file);
+        wtr.serialize(&CsvAllocation {
+            recipient: alice_pubkey.to_string(),
+            amount: "42".to_string(),
+            lockup_date: Some(lockup_date.to_rfc3339()),
+        })
+        .unwrap();
+        wtr.flush().unwrap();
+
+        assert_eq!(
+            read_allocations(&input_csv, false, None, 9),
+            vec![allocation]
+        );
     }
 
     #[test]
@@ -785,7 +1565,7 @@ mod tests {
         let alice_pubkey = Pubkey::new_rand();
         let bid = Bid {
             primary_address: alice_pubkey.to_string(),
-            accepted_amount_dollars: 42.0,
+            accepted_amount_dollars: "42.0".to_string(),
         };
         let file = NamedTempFile::new().unwrap();
         let input_csv = file.path().to_str().unwrap().to_string();
@@ -795,10 +1575,11 @@ mod tests {
 
         let allocation = Allocation {
             recipient: bid.primary_address,
-            amount: 84.0,
+            amount: sol_to_lamports(84.0),
+            lockup_date: None,
         };
         assert_eq!(
-            read_allocations(&input_csv, true, Some(0.5)),
+            read_allocations(&input_csv, true, Some(0.5), 9),
             vec![allocation]
         );
     }
@@ -808,19 +1589,26 @@ mod tests {
         let mut allocations = vec![
             Allocation {
                 recipient: "a".to_string(),
-                amount: 1.0,
+                amount: 1,
+                lockup_date: None,
             },
             Allocation {
                 recipient: "b".to_string(),
-                amount: 1.0,
+                amount: 1,
+                lockup_date: None,
             },
         ];
         let transaction_infos = vec![TransactionInfo {
-            recipient: "b".to_string(),
-            amount: 1.0,
+            recipients: vec![RecipientAllocation {
+                recipient: "b".to_string(),
+                amount: 1,
+                lockup_date: None,
+            }],
+            decimals: 9,
             new_stake_account_address: "".to_string(),
-            finalized: true,
+            finalized_date: Some(Utc::now()),
             blockhash: Hash::default().to_string(),
+            last_valid_slot: 0,
         }];
         apply_previous_transactions(&mut allocations, &transaction_infos);
         assert_eq!(allocations.len(), 1);
@@ -836,12 +1624,22 @@ mod tests {
         let mut db =
             PickleDb::new_yaml(NamedTempFile::new().unwrap(), PickleDbDumpPolicy::NeverDump);
         let signature = Signature::default();
-        let blockhash = Hash::default();
-        let transaction_info = TransactionInfo::default();
+        let last_valid_slot: Slot = 42;
+        let transaction_info = TransactionInfo {
+            last_valid_slot,
+            ..TransactionInfo::default()
+        };
         db.set(&signature.to_string(), &transaction_info).unwrap();
         assert_eq!(
-            update_finalized_transaction(&mut db, &signature, None, &blockhash, &[blockhash])
-                .unwrap(),
+            update_finalized_transaction(
+                &mut db,
+                &signature,
+                None,
+                last_valid_slot,
+                last_valid_slot,
+                CommitmentConfig::finalized(),
+            )
+            .unwrap(),
             Some(0)
         );
 
@@ -853,7 +1651,15 @@ mod tests {
 
         // Same as before, but now with an expired blockhash
         assert_eq!(
-            update_finalized_transaction(&mut db, &signature, None, &blockhash, &[]).unwrap(),
+            update_finalized_transaction(
+                &mut db,
+                &signature,
+                None,
+                last_valid_slot,
+                last_valid_slot + 1,
+                CommitmentConfig::finalized(),
+            )
+            .unwrap(),
             None
         );
 
@@ -867,7 +1673,6 @@ mod tests {
         let mut db =
             PickleDb::new_yaml(NamedTempFile::new().unwrap(), PickleDbDumpPolicy::NeverDump);
         let signature = Signature::default();
-        let blockhash = Hash::default();
         let transaction_info = TransactionInfo::default();
         db.set(&signature.to_string(), &transaction_info).unwrap();
         let transaction_status = TransactionStatus {
@@ -881,8 +1686,9 @@ mod tests {
                 &mut db,
                 &signature,
                 Some(transaction_status),
-                &blockhash,
-                &[blockhash]
+                0,
+                0,
+                CommitmentConfig::finalized(),
             )
             .unwrap(),
             Some(1)
@@ -895,13 +1701,51 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_update_finalized_transaction_confirmed_commitment() {
+        // At `confirmed` commitment, any observed status is enough -- no need to wait for
+        // the transaction to be rooted.
+        let mut db =
+            PickleDb::new_yaml(NamedTempFile::new().unwrap(), PickleDbDumpPolicy::NeverDump);
+        let signature = Signature::default();
+        let transaction_info = TransactionInfo::default();
+        db.set(&signature.to_string(), &transaction_info).unwrap();
+        let transaction_status = TransactionStatus {
+            slot: 0,
+            confirmations: Some(1),
+            status: Ok(()),
+            err: None,
+        };
+        assert_eq!(
+            update_finalized_transaction(
+                &mut db,
+                &signature,
+                Some(transaction_status),
+                0,
+                0,
+                CommitmentConfig::confirmed(),
+            )
+            .unwrap(),
+            None
+        );
+
+        let updated_info = db.get::<TransactionInfo>(&signature.to_string()).unwrap();
+        assert!(updated_info.finalized_date.is_some());
+        assert_eq!(
+            updated_info,
+            TransactionInfo {
+                finalized_date: updated_info.finalized_date,
+                ..transaction_info
+            }
+        );
+    }
+
     #[test]
     fn test_update_finalized_transaction_failed() {
         // Don't wait if the transaction failed to execute.
         let mut db =
             PickleDb::new_yaml(NamedTempFile::new().unwrap(), PickleDbDumpPolicy::NeverDump);
         let signature = Signature::default();
-        let blockhash = Hash::default();
         let transaction_info = TransactionInfo::default();
         db.set(&signature.to_string(), &transaction_info).unwrap();
         let status = Err(TransactionError::AccountNotFound);
@@ -916,8 +1760,9 @@ mod tests {
                 &mut db,
                 &signature,
                 Some(transaction_status),
-                &blockhash,
-                &[blockhash]
+                0,
+                0,
+                CommitmentConfig::finalized(),
             )
             .unwrap(),
             None
@@ -933,8 +1778,7 @@ mod tests {
         let mut db =
             PickleDb::new_yaml(NamedTempFile::new().unwrap(), PickleDbDumpPolicy::NeverDump);
         let signature = Signature::default();
-        let blockhash = Hash::default();
-        let mut transaction_info = TransactionInfo::default();
+        let transaction_info = TransactionInfo::default();
         db.set(&signature.to_string(), &transaction_info).unwrap();
         let transaction_status = TransactionStatus {
             slot: 0,
@@ -947,17 +1791,22 @@ mod tests {
                 &mut db,
                 &signature,
                 Some(transaction_status),
-                &blockhash,
-                &[blockhash]
+                0,
+                0,
+                CommitmentConfig::finalized(),
             )
             .unwrap(),
             None
         );
 
-        transaction_info.finalized = true;
+        let updated_info = db.get::<TransactionInfo>(&signature.to_string()).unwrap();
+        assert!(updated_info.finalized_date.is_some());
         assert_eq!(
-            db.get::<TransactionInfo>(&signature.to_string()).unwrap(),
-            transaction_info
+            updated_info,
+            TransactionInfo {
+                finalized_date: updated_info.finalized_date,
+                ..transaction_info
+            }
         );
     }
 
@@ -966,7 +1815,43 @@ mod tests {
         let mut db =
             PickleDb::new_yaml(NamedTempFile::new().unwrap(), PickleDbDumpPolicy::NeverDump);
         let signature = Signature::default();
-        let transaction_info = TransactionInfo::default();
+        let finalized_date: DateTime<Utc> = "2021-01-02T03:04:05Z".parse().unwrap();
+        let transaction_info = TransactionInfo {
+            recipients: vec![RecipientAllocation::default()],
+            finalized_date: Some(finalized_date),
+            ..TransactionInfo::default()
+        };
+        db.set(&signature.to_string(), &transaction_info).unwrap();
+
+        let csv_file = NamedTempFile::new().unwrap();
+        write_transaction_log(&db, &csv_file).unwrap();
+
+        let mut rdr = ReaderBuilder::new().trim(Trim::All).from_reader(csv_file);
+        let signed_infos: Vec<SignedTransactionInfo> =
+            rdr.deserialize().map(|entry| entry.unwrap()).collect();
+
+        let signed_info = SignedTransactionInfo {
+            signature: Signature::default().to_string(),
+            amount: "0".to_string(),
+            finalized_date: Some(finalized_date.to_rfc3339()),
+            ..SignedTransactionInfo::default()
+        };
+        assert_eq!(signed_infos, vec![signed_info]);
+    }
+
+    #[test]
+    fn test_write_transaction_log_with_lockup_date() {
+        let mut db =
+            PickleDb::new_yaml(NamedTempFile::new().unwrap(), PickleDbDumpPolicy::NeverDump);
+        let signature = Signature::default();
+        let lockup_date: DateTime<Utc> = "2021-01-02T03:04:05Z".parse().unwrap();
+        let transaction_info = TransactionInfo {
+            recipients: vec![RecipientAllocation {
+                lockup_date: Some(lockup_date),
+                ..RecipientAllocation::default()
+            }],
+            ..TransactionInfo::default()
+        };
         db.set(&signature.to_string(), &transaction_info).unwrap();
 
         let csv_file = NamedTempFile::new().unwrap();
@@ -978,6 +1863,8 @@ mod tests {
 
         let signed_info = SignedTransactionInfo {
             signature: Signature::default().to_string(),
+            amount: "0".to_string(),
+            lockup_date: Some(lockup_date.to_rfc3339()),
             ..SignedTransactionInfo::default()
         };
         assert_eq!(signed_infos, vec![signed_info]);